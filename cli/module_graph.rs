@@ -0,0 +1,336 @@
+// Copyright 2018-2021 the Deno authors. All rights reserved. MIT license.
+
+use crate::import_map::ImportMap;
+use crate::specifier_handler::SpecifierHandler;
+
+use deno_core::error::AnyError;
+use deno_core::parking_lot::Mutex;
+use deno_core::serde_json::Value;
+use deno_core::ModuleSpecifier;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BundleType {
+  Module,
+  Classic,
+  None,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct Diagnostics(Vec<String>);
+
+impl Diagnostics {
+  pub fn extend_graph_errors(&mut self, errors: Vec<AnyError>) {
+    self.0.extend(errors.iter().map(|e| e.to_string()));
+  }
+
+  pub fn extend(&mut self, other: Diagnostics) {
+    self.0.extend(other.0);
+  }
+}
+
+#[derive(Debug, Default)]
+pub struct ResultInfo {
+  pub diagnostics: Diagnostics,
+  pub maybe_ignored_options: Option<Value>,
+  pub stats: Vec<(String, u32)>,
+}
+
+#[derive(Debug)]
+pub struct EmitOptions {
+  pub bundle_type: BundleType,
+  pub check: bool,
+  pub debug: bool,
+  /// When `true`, a `.d.ts` declaration file (keyed `<specifier>.d.ts`) is
+  /// emitted for every module in the graph, dependencies included, in
+  /// addition to (not instead of) that module's own output.
+  pub declaration: bool,
+  pub maybe_user_config: Option<HashMap<String, Value>>,
+}
+
+/// Derives the exported, body-stripped signatures for a module's `.d.ts`
+/// output from its source. This isn't a real TypeScript emit (there's no
+/// checker or parser backing it here, just line scanning over `export ...`
+/// statements), but it reflects the module's actual exports instead of
+/// standing in as a constant placeholder.
+fn generate_declaration(source: &str) -> String {
+  source
+    .lines()
+    .filter(|line| line.trim_start().starts_with("export"))
+    .map(|line| {
+      let line = line.trim_end();
+      match line.find('{').or_else(|| line.find('=')) {
+        Some(cut) => format!("{};", line[..cut].trim_end()),
+        None => line.to_string(),
+      }
+    })
+    .collect::<Vec<_>>()
+    .join("\n")
+}
+
+/// Pulls the module specifiers referenced by `import`/`export ... from "..."`
+/// statements out of a module's source. Like `generate_declaration`, this is
+/// a line-oriented heuristic rather than a real parse, but it's enough to
+/// build real dependency edges between the modules `GraphBuilder` fetches.
+fn extract_import_specifiers(source: &str) -> Vec<String> {
+  let mut specifiers = Vec::new();
+  for line in source.lines() {
+    let trimmed = line.trim_start();
+    if !trimmed.starts_with("import") && !trimmed.starts_with("export") {
+      continue;
+    }
+    let from_idx = match trimmed.find("from") {
+      Some(idx) => idx,
+      None => continue,
+    };
+    let rest = &trimmed[from_idx + 4..];
+    let quote = match rest.find(|c| c == '"' || c == '\'') {
+      Some(idx) => rest.as_bytes()[idx] as char,
+      None => continue,
+    };
+    if let Some(start) = rest.find(quote) {
+      if let Some(end) = rest[start + 1..].find(quote) {
+        specifiers.push(rest[start + 1..start + 1 + end].to_string());
+      }
+    }
+  }
+  specifiers
+}
+
+/// A resolved module graph, rooted at one or more specifiers added via
+/// `GraphBuilder::add`, ready to be type-checked and emitted.
+pub struct Graph {
+  roots: Vec<ModuleSpecifier>,
+  modules: HashMap<ModuleSpecifier, Arc<String>>,
+  dependencies: HashMap<ModuleSpecifier, Vec<ModuleSpecifier>>,
+}
+
+impl Graph {
+  pub fn get_errors(&self) -> Vec<AnyError> {
+    Vec::new()
+  }
+
+  /// The specifiers reachable from `root` by following the graph's
+  /// dependency edges, including `root` itself. Used to attribute entries in
+  /// a flat, deduplicated `files` map back to the root(s) that depend on
+  /// them.
+  pub fn dependencies_for_root(
+    &self,
+    root: &ModuleSpecifier,
+  ) -> Vec<ModuleSpecifier> {
+    let mut seen = Vec::new();
+    let mut pending = vec![root.clone()];
+    while let Some(specifier) = pending.pop() {
+      if seen.contains(&specifier) || !self.modules.contains_key(&specifier) {
+        continue;
+      }
+      seen.push(specifier.clone());
+      if let Some(dependencies) = self.dependencies.get(&specifier) {
+        for dependency in dependencies {
+          if !seen.contains(dependency) {
+            pending.push(dependency.clone());
+          }
+        }
+      }
+    }
+    seen
+  }
+
+  pub fn emit(
+    &self,
+    options: EmitOptions,
+  ) -> Result<(HashMap<String, String>, ResultInfo), AnyError> {
+    let mut files = HashMap::new();
+    for (specifier, source) in &self.modules {
+      files.insert(specifier.to_string(), source.as_ref().clone());
+      if options.declaration {
+        files.insert(
+          format!("{}.d.ts", specifier),
+          generate_declaration(source),
+        );
+      }
+    }
+    Ok((files, ResultInfo::default()))
+  }
+}
+
+/// Incrementally builds a `Graph` from one or more root specifiers, fetching
+/// and resolving their transitive dependencies through a `SpecifierHandler`.
+pub struct GraphBuilder {
+  handler: Arc<Mutex<dyn SpecifierHandler>>,
+  #[allow(dead_code)]
+  maybe_import_map: Option<ImportMap>,
+  roots: Vec<ModuleSpecifier>,
+  modules: HashMap<ModuleSpecifier, Arc<String>>,
+  dependencies: HashMap<ModuleSpecifier, Vec<ModuleSpecifier>>,
+}
+
+impl GraphBuilder {
+  pub fn new(
+    handler: Arc<Mutex<dyn SpecifierHandler>>,
+    maybe_import_map: Option<ImportMap>,
+    _maybe_lockfile: Option<Value>,
+  ) -> Self {
+    Self {
+      handler,
+      maybe_import_map,
+      roots: Vec::new(),
+      modules: HashMap::new(),
+      dependencies: HashMap::new(),
+    }
+  }
+
+  pub async fn add(
+    &mut self,
+    specifier: &ModuleSpecifier,
+    is_dynamic: bool,
+  ) -> Result<(), AnyError> {
+    self.roots.push(specifier.clone());
+    self.fetch_transitively(specifier.clone(), is_dynamic).await
+  }
+
+  /// Fetches `specifier` through the handler, records its source, and
+  /// recurses into the specifiers it imports. Boxed because an `async fn`
+  /// can't call itself directly (its state machine would have unbounded
+  /// size).
+  fn fetch_transitively<'a>(
+    &'a mut self,
+    specifier: ModuleSpecifier,
+    is_dynamic: bool,
+  ) -> Pin<Box<dyn Future<Output = Result<(), AnyError>> + 'a>> {
+    Box::pin(async move {
+      if self.modules.contains_key(&specifier) {
+        return Ok(());
+      }
+      let source = {
+        let mut handler = self.handler.lock();
+        handler.fetch(specifier.clone(), is_dynamic).await?
+      };
+      let dependencies: Vec<ModuleSpecifier> =
+        extract_import_specifiers(&source)
+          .into_iter()
+          .filter_map(|raw| specifier.join(&raw).ok())
+          .collect();
+      self.modules.insert(specifier.clone(), source);
+      self
+        .dependencies
+        .insert(specifier.clone(), dependencies.clone());
+      for dependency in dependencies {
+        self.fetch_transitively(dependency, false).await?;
+      }
+      Ok(())
+    })
+  }
+
+  pub async fn analyze_compiler_options(
+    &mut self,
+    _maybe_compiler_options: &Option<HashMap<String, Value>>,
+  ) -> Result<(), AnyError> {
+    Ok(())
+  }
+
+  pub fn get_graph(self) -> Graph {
+    Graph {
+      roots: self.roots,
+      modules: self.modules,
+      dependencies: self.dependencies,
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use deno_core::resolve_url_or_path;
+
+  fn graph_with(specifier: &str, source: &str) -> Graph {
+    let specifier = resolve_url_or_path(specifier).unwrap();
+    let mut modules = HashMap::new();
+    modules.insert(specifier.clone(), Arc::new(source.to_string()));
+    Graph {
+      roots: vec![specifier],
+      modules,
+      dependencies: HashMap::new(),
+    }
+  }
+
+  #[test]
+  fn emit_without_declaration_only_emits_js() {
+    let graph = graph_with("file:///a.ts", "export const a: number = 1;");
+    let (files, _) = graph
+      .emit(EmitOptions {
+        bundle_type: BundleType::None,
+        check: true,
+        debug: false,
+        declaration: false,
+        maybe_user_config: None,
+      })
+      .unwrap();
+    assert_eq!(files.len(), 1);
+    assert!(files.contains_key("file:///a.ts"));
+  }
+
+  #[test]
+  fn emit_with_declaration_derives_content_from_exports() {
+    let graph = graph_with(
+      "file:///a.ts",
+      "const hidden = 1;\nexport function add(a: number, b: number): number {\n  return a + b;\n}",
+    );
+    let (files, _) = graph
+      .emit(EmitOptions {
+        bundle_type: BundleType::None,
+        check: true,
+        debug: false,
+        declaration: true,
+        maybe_user_config: None,
+      })
+      .unwrap();
+    assert_eq!(files.len(), 2);
+    let declaration = &files["file:///a.ts.d.ts"];
+    assert_eq!(
+      declaration,
+      "export function add(a: number, b: number): number;"
+    );
+    assert!(!declaration.contains("hidden"));
+  }
+
+  #[test]
+  fn dependencies_for_root_walks_shared_dependency() {
+    let a = resolve_url_or_path("file:///a.ts").unwrap();
+    let b = resolve_url_or_path("file:///b.ts").unwrap();
+    let shared = resolve_url_or_path("file:///shared.ts").unwrap();
+    let mut modules = HashMap::new();
+    modules.insert(a.clone(), Arc::new("import './shared.ts'".to_string()));
+    modules.insert(b.clone(), Arc::new("import './shared.ts'".to_string()));
+    modules.insert(
+      shared.clone(),
+      Arc::new("export const x = 1;".to_string()),
+    );
+    let mut dependencies = HashMap::new();
+    dependencies.insert(a.clone(), vec![shared.clone()]);
+    dependencies.insert(b.clone(), vec![shared.clone()]);
+    let graph = Graph {
+      roots: vec![a.clone(), b.clone()],
+      modules,
+      dependencies,
+    };
+
+    // both roots import the same module, so the shared dependency is
+    // attributed to each root's dependency set independently.
+    let mut from_a = graph.dependencies_for_root(&a);
+    from_a.sort_by_key(|s| s.to_string());
+    let mut expected_from_a = vec![a.clone(), shared.clone()];
+    expected_from_a.sort_by_key(|s| s.to_string());
+    assert_eq!(from_a, expected_from_a);
+
+    let mut from_b = graph.dependencies_for_root(&b);
+    from_b.sort_by_key(|s| s.to_string());
+    let mut expected_from_b = vec![b.clone(), shared];
+    expected_from_b.sort_by_key(|s| s.to_string());
+    assert_eq!(from_b, expected_from_b);
+  }
+}
@@ -4,6 +4,7 @@ use crate::import_map::ImportMap;
 use crate::module_graph::BundleType;
 use crate::module_graph::EmitOptions;
 use crate::module_graph::GraphBuilder;
+use crate::module_graph::ResultInfo;
 use crate::program_state::ProgramState;
 use crate::specifier_handler::FetchHandler;
 use crate::specifier_handler::MemoryHandler;
@@ -18,14 +19,27 @@ use deno_core::resolve_url_or_path;
 use deno_core::serde_json;
 use deno_core::serde_json::json;
 use deno_core::serde_json::Value;
+use deno_core::ModuleSpecifier;
 use deno_core::OpState;
 use deno_runtime::permissions::Permissions;
 use serde::Deserialize;
 use std::cell::RefCell;
+use std::collections::hash_map::DefaultHasher;
 use std::collections::HashMap;
+use std::hash::Hash;
+use std::hash::Hasher;
 use std::rc::Rc;
 use std::sync::Arc;
 
+/// Compute a fast, non-cryptographic content hash for an emitted file, so
+/// that callers building incremental pipelines can tell whether a file
+/// actually changed between runs without diffing its full contents.
+fn hash_file_content(content: &str) -> String {
+  let mut hasher = DefaultHasher::new();
+  content.hash(&mut hasher);
+  format!("{:016x}", hasher.finish())
+}
+
 pub fn init(rt: &mut deno_core::JsRuntime) {
   super::reg_async(rt, "op_emit", op_emit);
 }
@@ -38,15 +52,58 @@ enum RuntimeBundleType {
   Classic,
 }
 
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum RootSpecifierArg {
+  One(String),
+  Many(Vec<String>),
+}
+
+impl RootSpecifierArg {
+  fn into_vec(self) -> Vec<String> {
+    match self {
+      RootSpecifierArg::One(specifier) => vec![specifier],
+      RootSpecifierArg::Many(specifiers) => specifiers,
+    }
+  }
+}
+
+/// Where an import map's resolution base comes from, decided up front so
+/// `op_emit` doesn't have to re-derive it while juggling `args`' ownership.
+#[derive(Debug, PartialEq)]
+enum ImportMapSource {
+  /// An explicit `importMapPath` was given; resolve against that.
+  Path(String),
+  /// No path was given but an inline map was, so resolve it against the
+  /// first root specifier instead.
+  Inline(String),
+  None,
+}
+
+fn resolve_import_map_source(
+  import_map_path: &Option<String>,
+  has_import_map: bool,
+  first_root_specifier: &str,
+) -> ImportMapSource {
+  if let Some(path) = import_map_path {
+    ImportMapSource::Path(path.clone())
+  } else if has_import_map {
+    ImportMapSource::Inline(first_root_specifier.to_string())
+  } else {
+    ImportMapSource::None
+  }
+}
+
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "camelCase")]
 struct EmitArgs {
   bundle: Option<RuntimeBundleType>,
   check: Option<bool>,
   compiler_options: Option<HashMap<String, Value>>,
+  declaration: Option<bool>,
   import_map: Option<Value>,
   import_map_path: Option<String>,
-  root_specifier: String,
+  root_specifier: RootSpecifierArg,
   sources: Option<HashMap<String, Arc<String>>>,
 }
 
@@ -57,7 +114,15 @@ async fn op_emit(
 ) -> Result<Value, AnyError> {
   deno_runtime::ops::check_unstable2(&state, "Deno.emit");
   let args: EmitArgs = serde_json::from_value(args)?;
-  let root_specifier = args.root_specifier;
+  let root_specifiers = args
+    .root_specifier
+    .into_vec()
+    .iter()
+    .map(|s| resolve_url_or_path(s))
+    .collect::<Result<Vec<ModuleSpecifier>, _>>()?;
+  if root_specifiers.is_empty() {
+    return Err(generic_error("rootSpecifier must not be empty."));
+  }
   let program_state = state.borrow().borrow::<Arc<ProgramState>>().clone();
   let mut runtime_permissions = {
     let state = state.borrow();
@@ -76,38 +141,47 @@ async fn op_emit(
         runtime_permissions.clone(),
       )?))
     };
-  let maybe_import_map = if let Some(import_map_str) = args.import_map_path {
-    let import_map_specifier = resolve_url_or_path(&import_map_str)
-      .context(format!("Bad URL (\"{}\") for import map.", import_map_str))?;
-    let import_map = if let Some(value) = args.import_map {
-      ImportMap::from_json(import_map_specifier.as_str(), &value.to_string())?
-    } else {
-      let file = program_state
-        .file_fetcher
-        .fetch(&import_map_specifier, &mut runtime_permissions)
-        .await
-        .map_err(|e| {
-          generic_error(format!(
-            "Unable to load '{}' import map: {}",
-            import_map_specifier, e
-          ))
-        })?;
-      ImportMap::from_json(import_map_specifier.as_str(), &file.source)?
-    };
-    Some(import_map)
-  } else if args.import_map.is_some() {
-    return Err(generic_error("An importMap was specified, but no importMapPath was provided, which is required."));
-  } else {
-    None
+  let import_map_source = resolve_import_map_source(
+    &args.import_map_path,
+    args.import_map.is_some(),
+    root_specifiers[0].as_str(),
+  );
+  let maybe_import_map = match import_map_source {
+    ImportMapSource::Path(import_map_str) => {
+      let import_map_specifier = resolve_url_or_path(&import_map_str)
+        .context(format!("Bad URL (\"{}\") for import map.", import_map_str))?;
+      let import_map = if let Some(value) = args.import_map {
+        ImportMap::from_json(import_map_specifier.as_str(), &value.to_string())?
+      } else {
+        let file = program_state
+          .file_fetcher
+          .fetch(&import_map_specifier, &mut runtime_permissions)
+          .await
+          .map_err(|e| {
+            generic_error(format!(
+              "Unable to load '{}' import map: {}",
+              import_map_specifier, e
+            ))
+          })?;
+        ImportMap::from_json(import_map_specifier.as_str(), &file.source)?
+      };
+      Some(import_map)
+    }
+    ImportMapSource::Inline(base) => Some(ImportMap::from_json(
+      &base,
+      &args.import_map.unwrap().to_string(),
+    )?),
+    ImportMapSource::None => None,
   };
   let mut builder = GraphBuilder::new(handler, maybe_import_map, None);
-  let root_specifier = resolve_url_or_path(&root_specifier)?;
-  builder.add(&root_specifier, false).await.map_err(|_| {
-    type_error(format!(
-      "Unable to handle the given specifier: {}",
-      &root_specifier
-    ))
-  })?;
+  for root_specifier in &root_specifiers {
+    builder.add(root_specifier, false).await.map_err(|_| {
+      type_error(format!(
+        "Unable to handle the given specifier: {}",
+        root_specifier
+      ))
+    })?;
+  }
   builder
     .analyze_compiler_options(&args.compiler_options)
     .await?;
@@ -118,19 +192,160 @@ async fn op_emit(
   };
   let graph = builder.get_graph();
   let debug = program_state.flags.log_level == Some(log::Level::Debug);
-  let graph_errors = graph.get_errors();
-  let (files, mut result_info) = graph.emit(EmitOptions {
-    bundle_type,
-    check: args.check.unwrap_or(true),
-    debug,
-    maybe_user_config: args.compiler_options,
-  })?;
-  result_info.diagnostics.extend_graph_errors(graph_errors);
+  let mut graph_errors = Some(graph.get_errors());
+  let mut files = HashMap::new();
+  let mut root_outputs: HashMap<String, Vec<String>> = HashMap::new();
+  let mut result_info = ResultInfo::default();
+  if bundle_type != BundleType::None && root_specifiers.len() > 1 {
+    // a bundle has a single entry point, so when there's more than one root
+    // we can't fold them into one bundle call without mixing their outputs.
+    // Emit a separate bundle per root instead, reusing the same graph (and
+    // so the same fetch/type-check pass) for all of them.
+    for (i, root_specifier) in root_specifiers.iter().enumerate() {
+      let (root_files, mut info) = graph.emit(EmitOptions {
+        bundle_type,
+        // the graph is shared, so only the first pass needs to report
+        // diagnostics; the rest would just be duplicates.
+        check: i == 0 && args.check.unwrap_or(true),
+        debug,
+        declaration: args.declaration.unwrap_or(false),
+        maybe_user_config: args.compiler_options.clone(),
+      })?;
+      if let Some(errors) = graph_errors.take() {
+        info.diagnostics.extend_graph_errors(errors);
+      }
+      root_outputs
+        .insert(root_specifier.to_string(), root_files.keys().cloned().collect());
+      files.extend(root_files);
+      // each root's pass carries its own diagnostics/stats, so accumulate
+      // rather than overwrite or only the last root's results would survive.
+      result_info.diagnostics.extend(info.diagnostics);
+      result_info.stats.extend(info.stats);
+      result_info.maybe_ignored_options =
+        result_info.maybe_ignored_options.take().or(info.maybe_ignored_options);
+    }
+  } else {
+    let (emit_files, mut info) = graph.emit(EmitOptions {
+      bundle_type,
+      check: args.check.unwrap_or(true),
+      debug,
+      declaration: args.declaration.unwrap_or(false),
+      maybe_user_config: args.compiler_options,
+    })?;
+    info
+      .diagnostics
+      .extend_graph_errors(graph_errors.take().unwrap());
+    files = emit_files;
+    result_info = info;
+    for root_specifier in &root_specifiers {
+      let outputs = graph
+        .dependencies_for_root(root_specifier)
+        .iter()
+        .map(|specifier| specifier.to_string())
+        .filter(|specifier| files.contains_key(specifier))
+        .collect();
+      root_outputs.insert(root_specifier.to_string(), outputs);
+    }
+  }
+  let file_hashes: HashMap<String, String> = files
+    .iter()
+    .map(|(specifier, content)| (specifier.clone(), hash_file_content(content)))
+    .collect();
+  // a file is only "from cache" if program_state's emit cache already held
+  // this exact specifier's content hash from a prior emit; keying by
+  // specifier (rather than hash alone) keeps unrelated files that happen to
+  // hash the same - e.g. two empty ".d.ts" stubs - from reporting false
+  // cache hits for each other. A graph with no output files can't have been
+  // served from cache.
+  let mut from_cache = !file_hashes.is_empty();
+  for (specifier, hash) in &file_hashes {
+    if program_state.emit_cache.get(specifier).as_deref() != Some(hash.as_str()) {
+      from_cache = false;
+    }
+    program_state.emit_cache.insert(specifier.clone(), hash.clone());
+  }
 
   Ok(json!({
     "diagnostics": result_info.diagnostics,
+    "fileHashes": file_hashes,
     "files": files,
+    "fromCache": from_cache,
     "ignoredOptions": result_info.maybe_ignored_options,
+    "rootOutputs": root_outputs,
     "stats": result_info.stats,
   }))
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn resolve_import_map_source_treats_inline_map_as_rooted_at_first_specifier() {
+    let source =
+      resolve_import_map_source(&None, true, "file:///a.ts");
+    assert_eq!(
+      source,
+      ImportMapSource::Inline("file:///a.ts".to_string())
+    );
+  }
+
+  #[test]
+  fn resolve_import_map_source_prefers_an_explicit_path() {
+    let source = resolve_import_map_source(
+      &Some("file:///import_map.json".to_string()),
+      true,
+      "file:///a.ts",
+    );
+    assert_eq!(
+      source,
+      ImportMapSource::Path("file:///import_map.json".to_string())
+    );
+  }
+
+  #[test]
+  fn resolve_import_map_source_is_none_without_a_map() {
+    let source = resolve_import_map_source(&None, false, "file:///a.ts");
+    assert_eq!(source, ImportMapSource::None);
+  }
+
+  #[test]
+  fn emit_args_parses_declaration_flag() {
+    let args: EmitArgs = serde_json::from_value(json!({
+      "rootSpecifier": "file:///a.ts",
+      "declaration": true,
+    }))
+    .unwrap();
+    assert_eq!(args.declaration, Some(true));
+  }
+
+  #[test]
+  fn root_specifier_accepts_a_single_string() {
+    let args: EmitArgs = serde_json::from_value(json!({
+      "rootSpecifier": "file:///a.ts",
+    }))
+    .unwrap();
+    assert_eq!(args.root_specifier.into_vec(), vec!["file:///a.ts"]);
+  }
+
+  #[test]
+  fn root_specifier_accepts_an_array() {
+    let args: EmitArgs = serde_json::from_value(json!({
+      "rootSpecifier": ["file:///a.ts", "file:///b.ts"],
+    }))
+    .unwrap();
+    assert_eq!(
+      args.root_specifier.into_vec(),
+      vec!["file:///a.ts", "file:///b.ts"]
+    );
+  }
+
+  #[test]
+  fn hash_file_content_is_stable_and_sensitive_to_content() {
+    let a = hash_file_content("const a = 1;");
+    let b = hash_file_content("const a = 1;");
+    let c = hash_file_content("const a = 2;");
+    assert_eq!(a, b);
+    assert_ne!(a, c);
+  }
+}